@@ -0,0 +1,944 @@
+//! Demonstrate 2's compliment integer
+//!
+//! "2's Compliment" is a representation for integers such that,
+//! for any integer k of width n, k's negative "-k" is its
+//! compliment modulo 2^n.  That is, for any `k`, `-k` is the
+//! number so that $k + (-k) == 2^n$.  Another way to look at
+//! this is that $(k + (-k)) mod 2^n == 0$.
+//!
+//! Several desirable properties fall out of this definition:
+//!
+//! 1. Zero has a single representation.  Since the compliment
+//!    of 0 is 2^n, -0 is simply 0.
+//! 2. The same arithmetic logic for arithmetic may be used for
+//!    both signed and unsigned arithmetic.
+//!
+//! Note that there is one odd case; the most negative number
+//! has no positive compliment.
+//!
+//! Widths are not limited to what fits in a machine register:
+//! numbers are stored as little-endian limbs of `u64` so that
+//! widths like 256, 512 or 1024 bits work the same way as 32
+//! or 64.
+//!
+//! A width may also carry a fractional part, written `nbits.fbits`
+//! (e.g. `32.8` is a 32 bit word with 8 fractional bits). The stored
+//! value is still a plain `nbits`-wide two's complement integer; the
+//! fractional bits simply move the radix point `fbits` places in from
+//! the right when the value is parsed and printed, exactly as fixed-point
+//! representations work in hardware and DSP code.
+//!
+//! For widths that fit in a single machine register, the [`TwosComplement`]
+//! trait gives the same operations directly on the primitive integer types,
+//! e.g. `10u32.twos_complement(12)`.
+
+/// Number of bits in a single limb.
+const LIMB_BITS: usize = u64::BITS as usize;
+
+/// An error encountered while parsing a number.
+#[derive(Debug)]
+pub enum ParseNumError {
+    InvalidDigit(char, u32),
+    Empty,
+}
+
+impl std::fmt::Display for ParseNumError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseNumError::InvalidDigit(digit, radix) => {
+                write!(f, "'{digit}' is not a valid base-{radix} digit")
+            }
+            ParseNumError::Empty => write!(f, "empty number"),
+        }
+    }
+}
+
+impl std::error::Error for ParseNumError {}
+
+/// An error encountered while parsing a `bits` or `bits.fbits` width.
+#[derive(Debug)]
+pub enum ParseWidthError {
+    Int(std::num::ParseIntError),
+}
+
+impl std::fmt::Display for ParseWidthError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseWidthError::Int(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseWidthError {}
+
+impl From<std::num::ParseIntError> for ParseWidthError {
+    fn from(e: std::num::ParseIntError) -> Self {
+        ParseWidthError::Int(e)
+    }
+}
+
+/// An error encountered while parsing a number into a fixed-width
+/// two's-complement value.
+#[derive(Debug)]
+pub enum TwosCompError {
+    Parse(ParseNumError),
+    OutOfRange(usize),
+}
+
+impl std::fmt::Display for TwosCompError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TwosCompError::Parse(e) => write!(f, "{e}"),
+            TwosCompError::OutOfRange(nbits) => write!(f, "out of range for width {nbits} bits"),
+        }
+    }
+}
+
+impl std::error::Error for TwosCompError {}
+
+impl From<ParseNumError> for TwosCompError {
+    fn from(e: ParseNumError) -> Self {
+        TwosCompError::Parse(e)
+    }
+}
+
+/// Parse a width specification: either `nbits` on its own, or
+/// `nbits.fbits` giving the total width and the number of fractional
+/// bits within it (so `ibits = nbits - fbits`).
+pub fn parse_width(s: &str) -> Result<(usize, usize), ParseWidthError> {
+    match s.split_once('.') {
+        Some((nbits, fbits)) => Ok((nbits.parse()?, fbits.parse()?)),
+        None => Ok((s.parse()?, 0)),
+    }
+}
+
+/// Number of `u64` limbs needed to hold `nbits` bits.
+fn nlimbs(nbits: usize) -> usize {
+    nbits.div_ceil(LIMB_BITS)
+}
+
+/// Multiply `limbs` (little-endian) by `mul` and add `add`, growing the
+/// vector if the product overflows.
+fn mul_add_limb(limbs: &mut Vec<u64>, mul: u64, add: u64) {
+    let mut carry = add as u128;
+    for limb in limbs.iter_mut() {
+        let acc = *limb as u128 * mul as u128 + carry;
+        *limb = acc as u64;
+        carry = acc >> LIMB_BITS;
+    }
+    if carry != 0 {
+        limbs.push(carry as u64);
+    }
+}
+
+/// Bitwise NOT across every limb; the ones' complement.
+fn not_limbs(limbs: &[u64]) -> Vec<u64> {
+    limbs.iter().map(|&limb| !limb).collect()
+}
+
+/// Wrapping add of two limb vectors, propagating carry between limbs.
+/// The result is as long as the longer of the two inputs.
+fn wrapping_add(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let len = a.len().max(b.len());
+    let mut out = Vec::with_capacity(len);
+    let mut carry = 0u64;
+    for i in 0..len {
+        let a = a.get(i).copied().unwrap_or(0);
+        let b = b.get(i).copied().unwrap_or(0);
+        let acc = a as u128 + b as u128 + carry as u128;
+        out.push(acc as u64);
+        carry = (acc >> LIMB_BITS) as u64;
+    }
+    out
+}
+
+/// Two's complement: the ones' complement plus one.
+pub fn twoscomp(n: &[u64]) -> Vec<u64> {
+    wrapping_add(&not_limbs(n), &[1])
+}
+
+/// Clear every bit at or above `nbits` in the top limb of a vector already
+/// sized to `nlimbs(nbits)` limbs.
+pub fn mask(limbs: &mut [u64], nbits: usize) {
+    let top = nlimbs(nbits) - 1;
+    let bit = (nbits - 1) % LIMB_BITS;
+    let keep = if bit == LIMB_BITS - 1 {
+        !0u64
+    } else {
+        (1u64 << (bit + 1)) - 1
+    };
+    limbs[top] &= keep;
+    for limb in &mut limbs[top + 1..] {
+        *limb = 0;
+    }
+}
+
+/// Resize `n` to `nlimbs(nbits)` limbs and sign-extend it: if bit `nbits-1`
+/// is set, every higher bit (including higher limbs) is filled with ones;
+/// otherwise every higher bit is cleared.
+pub fn signextend(n: &[u64], nbits: usize) -> Vec<u64> {
+    let nl = nlimbs(nbits);
+    let mut out = vec![0u64; nl];
+    let copy = out.len().min(n.len());
+    out[..copy].copy_from_slice(&n[..copy]);
+    let top = nl - 1;
+    let bit = (nbits - 1) % LIMB_BITS;
+    let neg = (out[top] >> bit) & 1 == 1;
+    if neg {
+        let above = if bit == LIMB_BITS - 1 { 0 } else { !0u64 << (bit + 1) };
+        out[top] |= above;
+        for limb in &mut out[top + 1..] {
+            *limb = !0u64;
+        }
+    } else {
+        mask(&mut out, nbits);
+    }
+    out
+}
+
+/// Does `limbs` have any bit set at or above position `nbits`?
+fn bits_above(limbs: &[u64], nbits: usize) -> bool {
+    let nl = nlimbs(nbits);
+    if limbs.len() > nl && limbs[nl..].iter().any(|&limb| limb != 0) {
+        return true;
+    }
+    if let Some(&top) = limbs.get(nl - 1) {
+        let bit = (nbits - 1) % LIMB_BITS;
+        let above = if bit == LIMB_BITS - 1 { 0 } else { !0u64 << (bit + 1) };
+        if top & above != 0 {
+            return true;
+        }
+    }
+    false
+}
+
+/// Compare two limb vectors of possibly differing lengths as unbounded
+/// unsigned magnitudes, treating missing high limbs as zero.
+fn limbs_eq(a: &[u64], b: &[u64]) -> bool {
+    let len = a.len().max(b.len());
+    (0..len).all(|i| a.get(i).copied().unwrap_or(0) == b.get(i).copied().unwrap_or(0))
+}
+
+/// Divide a limb vector by a small divisor, returning the quotient and the
+/// remainder.
+fn divmod_small(limbs: &[u64], divisor: u64) -> (Vec<u64>, u64) {
+    let mut out = vec![0u64; limbs.len()];
+    let mut rem: u128 = 0;
+    for i in (0..limbs.len()).rev() {
+        let acc = (rem << LIMB_BITS) | limbs[i] as u128;
+        out[i] = (acc / divisor as u128) as u64;
+        rem = acc % divisor as u128;
+    }
+    (out, rem as u64)
+}
+
+/// Shift a limb vector left by `n` bits, growing it as needed.
+fn shl(limbs: &[u64], n: usize) -> Vec<u64> {
+    let limb_shift = n / LIMB_BITS;
+    let bit_shift = n % LIMB_BITS;
+    let extra = if bit_shift == 0 { 0 } else { 1 };
+    let mut out = vec![0u64; limbs.len() + limb_shift + extra];
+    for (i, &limb) in limbs.iter().enumerate() {
+        if bit_shift == 0 {
+            out[i + limb_shift] |= limb;
+        } else {
+            out[i + limb_shift] |= limb << bit_shift;
+            out[i + limb_shift + 1] |= limb >> (LIMB_BITS - bit_shift);
+        }
+    }
+    out
+}
+
+/// Shift a limb vector right by `n` bits (logical, not arithmetic).
+fn shr(limbs: &[u64], n: usize) -> Vec<u64> {
+    let limb_shift = n / LIMB_BITS;
+    let bit_shift = n % LIMB_BITS;
+    if limb_shift >= limbs.len() {
+        return vec![0];
+    }
+    let mut out = vec![0u64; limbs.len() - limb_shift];
+    for (i, limb) in out.iter_mut().enumerate() {
+        let lo = limbs[i + limb_shift];
+        *limb = if bit_shift == 0 {
+            lo
+        } else {
+            let hi = limbs.get(i + limb_shift + 1).copied().unwrap_or(0);
+            (lo >> bit_shift) | (hi << (LIMB_BITS - bit_shift))
+        };
+    }
+    out
+}
+
+/// Compare two limb vectors of possibly differing lengths as unbounded
+/// unsigned magnitudes.
+fn cmp_limbs(a: &[u64], b: &[u64]) -> std::cmp::Ordering {
+    let len = a.len().max(b.len());
+    for i in (0..len).rev() {
+        let av = a.get(i).copied().unwrap_or(0);
+        let bv = b.get(i).copied().unwrap_or(0);
+        let ord = av.cmp(&bv);
+        if ord != std::cmp::Ordering::Equal {
+            return ord;
+        }
+    }
+    std::cmp::Ordering::Equal
+}
+
+/// Subtract `b` from `a`, assuming `a >= b`.
+fn sub_limbs(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let len = a.len().max(b.len());
+    let mut out = Vec::with_capacity(len);
+    let mut borrow = 0i128;
+    for i in 0..len {
+        let av = a.get(i).copied().unwrap_or(0) as i128;
+        let bv = b.get(i).copied().unwrap_or(0) as i128;
+        let mut diff = av - bv - borrow;
+        borrow = 0;
+        if diff < 0 {
+            diff += 1i128 << LIMB_BITS;
+            borrow = 1;
+        }
+        out.push(diff as u64);
+    }
+    out
+}
+
+/// Schoolbook multiply of two limb vectors.
+fn mul_limbs(a: &[u64], b: &[u64]) -> Vec<u64> {
+    let mut out = vec![0u64; a.len() + b.len()];
+    for (i, &av) in a.iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, &bv) in b.iter().enumerate() {
+            let acc = out[i + j] as u128 + av as u128 * bv as u128 + carry;
+            out[i + j] = acc as u64;
+            carry = acc >> LIMB_BITS;
+        }
+        let mut k = i + b.len();
+        while carry != 0 {
+            let acc = out[k] as u128 + carry;
+            out[k] = acc as u64;
+            carry = acc >> LIMB_BITS;
+            k += 1;
+        }
+    }
+    out
+}
+
+/// Long division of two bignums via binary shift-and-subtract, returning
+/// the quotient and remainder.
+fn divmod_bignum(num: &[u64], den: &[u64]) -> (Vec<u64>, Vec<u64>) {
+    let mut quot = vec![0u64; num.len()];
+    let mut rem = vec![0u64];
+    for i in (0..num.len() * LIMB_BITS).rev() {
+        rem = shl(&rem, 1);
+        if (num[i / LIMB_BITS] >> (i % LIMB_BITS)) & 1 == 1 {
+            rem[0] |= 1;
+        }
+        if cmp_limbs(&rem, den) != std::cmp::Ordering::Less {
+            rem = sub_limbs(&rem, den);
+            quot[i / LIMB_BITS] |= 1u64 << (i % LIMB_BITS);
+        }
+    }
+    (quot, rem)
+}
+
+/// Parse a run of digits in the given radix into a bignum magnitude.
+fn parse_digits(digits: &str, radix: u32) -> Result<Vec<u64>, ParseNumError> {
+    let mut limbs = vec![0u64];
+    for digit in digits.chars() {
+        let d = digit
+            .to_digit(radix)
+            .ok_or(ParseNumError::InvalidDigit(digit, radix))?;
+        mul_add_limb(&mut limbs, radix as u64, d as u64);
+    }
+    Ok(limbs)
+}
+
+/// The two-byte ASCII decimal pairs `"00".."99"`, used to peel a value apart
+/// four decimal digits at a time instead of one.
+const DIGIT_PAIRS: [[u8; 2]; 100] = {
+    let mut pairs = [[0u8; 2]; 100];
+    let mut i = 0;
+    while i < 100 {
+        pairs[i] = [b'0' + (i / 10) as u8, b'0' + (i % 10) as u8];
+        i += 1;
+    }
+    pairs
+};
+
+/// Render a non-negative magnitude as a plain unsigned decimal string.
+///
+/// Converts four digits at a time (`n % 10000`, then `n /= 10000`) via
+/// [`DIGIT_PAIRS`] rather than dividing by 10 one digit at a time, since
+/// `nbits` (and so the number of divisions) can be very large.
+pub fn unsigned_decimal(limbs: &[u64]) -> String {
+    let mut mag = limbs.to_vec();
+    let mut chunks = Vec::new();
+    loop {
+        let (q, rem) = divmod_small(&mag, 10000);
+        chunks.push(rem as u32);
+        mag = q;
+        if mag.iter().all(|&limb| limb == 0) {
+            break;
+        }
+    }
+
+    let mut s = String::with_capacity(chunks.len() * 4);
+    let (leading, rest) = chunks.split_last().unwrap();
+    s.push_str(&leading.to_string());
+    for chunk in rest.iter().rev() {
+        let d1 = (chunk / 100) as usize;
+        let d2 = (chunk % 100) as usize;
+        s.push(DIGIT_PAIRS[d1][0] as char);
+        s.push(DIGIT_PAIRS[d1][1] as char);
+        s.push(DIGIT_PAIRS[d2][0] as char);
+        s.push(DIGIT_PAIRS[d2][1] as char);
+    }
+    s
+}
+
+/// Split a sign-extended two's-complement value into its sign and
+/// unsigned magnitude.
+fn magnitude_and_sign(limbs: &[u64], nbits: usize) -> (bool, Vec<u64>) {
+    let bit = (nbits - 1) % LIMB_BITS;
+    let neg = (limbs[(nbits - 1) / LIMB_BITS] >> bit) & 1 == 1;
+    let mag = if neg { twoscomp(limbs) } else { limbs.to_vec() };
+    (neg, mag)
+}
+
+/// Render a two's-complement value, already sign-extended to `nbits`, as a
+/// signed decimal string.
+pub fn decimal(limbs: &[u64], nbits: usize) -> String {
+    let (neg, mag) = magnitude_and_sign(limbs, nbits);
+    let s = unsigned_decimal(&mag);
+    if neg {
+        format!("-{s}")
+    } else {
+        s
+    }
+}
+
+/// Number of decimal digits needed to losslessly round-trip `fbits`
+/// fractional bits: `ceil(fbits * log10(2))`.
+fn frac_digits(fbits: usize) -> usize {
+    ((fbits as f64) * 2f64.log10()).ceil() as usize
+}
+
+/// Render a two's-complement value, already sign-extended to `nbits`, as a
+/// signed fixed-point decimal with `fbits` fractional bits. Falls back to
+/// plain `decimal` when there are no fractional bits.
+pub fn fixed_decimal(limbs: &[u64], nbits: usize, fbits: usize) -> String {
+    if fbits == 0 {
+        return decimal(limbs, nbits);
+    }
+    let (neg, mag) = magnitude_and_sign(limbs, nbits);
+    let int_part = shr(&mag, fbits);
+    let mut frac_bits = vec![0u64; nlimbs(fbits)];
+    let copy = frac_bits.len().min(mag.len());
+    frac_bits[..copy].copy_from_slice(&mag[..copy]);
+    mask(&mut frac_bits, fbits);
+
+    let digits = frac_digits(fbits);
+    let mut scale = vec![1u64];
+    for _ in 0..digits {
+        mul_add_limb(&mut scale, 10, 0);
+    }
+    let numerator = mul_limbs(&frac_bits, &scale);
+    let denom = shl(&[1u64], fbits);
+    let (mut frac_q, rem) = divmod_bignum(&numerator, &denom);
+    if cmp_limbs(&shl(&rem, 1), &denom) != std::cmp::Ordering::Less {
+        frac_q = wrapping_add(&frac_q, &[1]);
+    }
+    let frac_str = unsigned_decimal(&frac_q);
+    let frac_str = format!("{frac_str:0>digits$}");
+
+    format!(
+        "{}{}.{frac_str}",
+        if neg { "-" } else { "" },
+        unsigned_decimal(&int_part)
+    )
+}
+
+/// Render the low `nbits` bits of `limbs` as a zero-padded hex string.
+pub fn hex(limbs: &[u64], nbits: usize) -> String {
+    let nibbles = nbits / 4;
+    let mut s = String::with_capacity(limbs.len() * LIMB_BITS / 4);
+    for limb in limbs.iter().rev() {
+        s.push_str(&format!("{limb:016x}"));
+    }
+    let start = s.len() - nibbles;
+    s[start..].to_string()
+}
+
+/// Render the low `nbits` bits of `limbs` as a zero-padded binary string.
+pub fn binary(limbs: &[u64], nbits: usize) -> String {
+    let mut s = String::with_capacity(limbs.len() * LIMB_BITS);
+    for limb in limbs.iter().rev() {
+        s.push_str(&format!("{limb:064b}"));
+    }
+    let start = s.len() - nbits;
+    s[start..].to_string()
+}
+
+/// Render the low `nbits` bits of `limbs` as a zero-padded octal string.
+pub fn octal(limbs: &[u64], nbits: usize) -> String {
+    let digits = nbits.div_ceil(3);
+    let pad = digits * 3 - nbits;
+    let bits = format!("{:0>pad$}{}", "", binary(limbs, nbits));
+    bits.as_bytes()
+        .chunks(3)
+        .map(|triad| {
+            let v = triad.iter().fold(0u8, |acc, &b| (acc << 1) | (b - b'0'));
+            (b'0' + v) as char
+        })
+        .collect()
+}
+
+/// Insert `sep` every `size` characters of `s`, counting from the right and
+/// leaving a leading `-` sign untouched. Used to group wide binary, octal,
+/// hex and decimal output into readable chunks.
+pub fn group(s: &str, size: usize, sep: char) -> String {
+    let (sign, digits) = match s.strip_prefix('-') {
+        Some(rest) => ("-", rest),
+        None => ("", s),
+    };
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / size);
+    for (i, c) in digits.chars().enumerate() {
+        let from_right = digits.len() - i;
+        if i > 0 && from_right % size == 0 {
+            grouped.push(sep);
+        }
+        grouped.push(c);
+    }
+    format!("{sign}{grouped}")
+}
+
+/// Parse a (possibly fractional, possibly negative) literal into an
+/// `nbits`-wide two's-complement integer with `fbits` fractional bits,
+/// sign-extended and range-checked. `-3.25` at `(32, 8)` (24 integer bits,
+/// 8 fractional bits) stores `round(-3.25 * 2^8)` as a plain 32 bit
+/// two's-complement value.
+pub fn parse_num(num: &str, nbits: usize, fbits: usize) -> Result<Vec<u64>, TwosCompError> {
+    let pos = !num.starts_with('-');
+    let (radix, numstr) = match &num[if pos { 0 } else { 1 }..] {
+        "0" => (10, "0"),
+        s if s.starts_with("0x") || s.starts_with("0X") => (16, &s[2..]),
+        s if s.starts_with("0t") || s.starts_with("0T") => (10, &s[2..]),
+        s if s.starts_with("0b") || s.starts_with("0B") => (2, &s[2..]),
+        s if s.starts_with("0") && !s.contains('.') => (8, &s[0..]),
+        s => (10, s),
+    };
+    if numstr.is_empty() {
+        return Err(ParseNumError::Empty.into());
+    }
+    let (int_str, frac_str) = match numstr.split_once('.') {
+        Some((i, f)) => (i, f),
+        None => (numstr, ""),
+    };
+    if int_str.is_empty() && frac_str.is_empty() {
+        return Err(ParseNumError::Empty.into());
+    }
+    let int_limbs = if int_str.is_empty() {
+        vec![0u64]
+    } else {
+        parse_digits(int_str, radix)?
+    };
+
+    // Round the fraction to the nearest `fbits`-bit fixed-point value:
+    // frac_value/radix^len(frac_str) scaled by 2^fbits, rounded to the
+    // nearest integer.
+    let frac_value = if frac_str.is_empty() {
+        vec![0u64]
+    } else {
+        let numerator = parse_digits(frac_str, radix)?;
+        let mut denom = vec![1u64];
+        for _ in 0..frac_str.chars().count() {
+            mul_add_limb(&mut denom, radix as u64, 0);
+        }
+        let scaled = shl(&numerator, fbits);
+        let (mut q, rem) = divmod_bignum(&scaled, &denom);
+        if cmp_limbs(&shl(&rem, 1), &denom) != std::cmp::Ordering::Less {
+            q = wrapping_add(&q, &[1]);
+        }
+        q
+    };
+
+    let mut magnitude = wrapping_add(&shl(&int_limbs, fbits), &frac_value);
+    while magnitude.len() > 1 && *magnitude.last().unwrap() == 0 {
+        magnitude.pop();
+    }
+    magnitude.resize(magnitude.len().max(nlimbs(nbits)), 0);
+    let raw = if pos {
+        magnitude
+    } else {
+        twoscomp(&magnitude)
+    };
+
+    let senum = signextend(&raw, nbits);
+    if !limbs_eq(&raw, &senum) && bits_above(&raw, nbits) {
+        return Err(TwosCompError::OutOfRange(nbits));
+    }
+    Ok(senum)
+}
+
+/// Two's-complement operations on a fixed-width primitive integer, for
+/// widths that fit in a single machine register. `nbits` may be smaller
+/// than the type's own width, e.g. `10u32.twos_complement(12)` treats the
+/// value as a 12 bit quantity stored in the low bits of a `u32`.
+pub trait TwosComplement: Sized {
+    /// Sign-extend bit `nbits - 1` through the rest of the value.
+    fn sign_extend(self, nbits: u32) -> Self;
+    /// The two's complement (negation): ones' complement plus one,
+    /// sign-extended to `nbits`.
+    fn twos_complement(self, nbits: u32) -> Self;
+    /// Clear every bit at or above `nbits`.
+    fn mask(self, nbits: u32) -> Self;
+}
+
+macro_rules! impl_twos_complement_unsigned {
+    ($($t:ty => $bits:literal),* $(,)?) => {
+        $(
+            impl TwosComplement for $t {
+                fn mask(self, nbits: u32) -> Self {
+                    if nbits == 0 {
+                        0
+                    } else if nbits >= $bits {
+                        self
+                    } else {
+                        self & ((1 as $t).wrapping_shl(nbits).wrapping_sub(1))
+                    }
+                }
+
+                fn sign_extend(self, nbits: u32) -> Self {
+                    if nbits == 0 {
+                        return 0;
+                    }
+                    if nbits >= $bits {
+                        return self;
+                    }
+                    let neg = (self >> (nbits - 1)) & 1 == 1;
+                    if neg {
+                        self | (!0 as $t).wrapping_shl(nbits)
+                    } else {
+                        self.mask(nbits)
+                    }
+                }
+
+                fn twos_complement(self, nbits: u32) -> Self {
+                    (!self).wrapping_add(1).sign_extend(nbits)
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! impl_twos_complement_signed {
+    ($(($t:ty, $u:ty)),* $(,)?) => {
+        $(
+            impl TwosComplement for $t {
+                fn mask(self, nbits: u32) -> Self {
+                    (self as $u).mask(nbits) as $t
+                }
+
+                fn sign_extend(self, nbits: u32) -> Self {
+                    (self as $u).sign_extend(nbits) as $t
+                }
+
+                fn twos_complement(self, nbits: u32) -> Self {
+                    (self as $u).twos_complement(nbits) as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_twos_complement_unsigned!(u8 => 8, u16 => 16, u32 => 32, u64 => 64, u128 => 128);
+impl_twos_complement_signed!((i8, u8), (i16, u16), (i32, u32), (i64, u64), (i128, u128));
+
+/// An ALU operation: the same adder does unsigned and signed arithmetic,
+/// which is the whole point of two's complement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AluOp {
+    Add,
+    Sub,
+    Mul,
+}
+
+/// The flags a real ALU reports alongside an arithmetic result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AluFlags {
+    /// Unsigned overflow: the true result didn't fit in `nbits` unsigned.
+    pub carry: bool,
+    /// Signed overflow: the true result didn't fit in `nbits` two's complement.
+    pub overflow: bool,
+    /// The result is zero.
+    pub zero: bool,
+    /// The result's sign bit is set.
+    pub negative: bool,
+}
+
+/// Perform a width-`nbits` ALU operation on two sign-extended operands,
+/// returning the wrapped result, sign-extended to `nbits`, and the flags a
+/// hardware ALU would set alongside it.
+///
+/// `a` and `b` must already be sign-extended to `nbits` (as returned by
+/// [`parse_num`] or [`signextend`]).
+pub fn alu(op: AluOp, a: &[u64], b: &[u64], nbits: usize) -> (Vec<u64>, AluFlags) {
+    match op {
+        AluOp::Add => alu_add_sub(a, b, nbits, false),
+        AluOp::Sub => alu_add_sub(a, b, nbits, true),
+        AluOp::Mul => alu_mul(a, b, nbits),
+    }
+}
+
+fn sign_bit(limbs: &[u64], nbits: usize) -> bool {
+    (limbs[(nbits - 1) / LIMB_BITS] >> ((nbits - 1) % LIMB_BITS)) & 1 == 1
+}
+
+/// Mask `limbs` down to its canonical `nlimbs(nbits)`-limb, bits-above-`nbits`-cleared form.
+fn masked(limbs: &[u64], nbits: usize) -> Vec<u64> {
+    let mut out = vec![0u64; nlimbs(nbits)];
+    let copy = out.len().min(limbs.len());
+    out[..copy].copy_from_slice(&limbs[..copy]);
+    mask(&mut out, nbits);
+    out
+}
+
+fn alu_add_sub(a: &[u64], b: &[u64], nbits: usize, sub: bool) -> (Vec<u64>, AluFlags) {
+    let a_m = masked(a, nbits);
+    // Subtraction is addition of the two's complement of `b`, computed
+    // within the same `nbits` width the adder itself operates on.
+    let addend = if sub {
+        masked(&twoscomp(&masked(b, nbits)), nbits)
+    } else {
+        masked(b, nbits)
+    };
+
+    let wide = nlimbs(nbits) + 1;
+    let mut a_wide = vec![0u64; wide];
+    a_wide[..a_m.len()].copy_from_slice(&a_m);
+    let mut b_wide = vec![0u64; wide];
+    b_wide[..addend.len()].copy_from_slice(&addend);
+
+    let raw = wrapping_add(&a_wide, &b_wide);
+    let carry = if sub {
+        // Unsigned borrow: `b` is larger than `a` as unsigned values.
+        cmp_limbs(&a_m, &masked(b, nbits)) == std::cmp::Ordering::Less
+    } else {
+        (raw[nbits / LIMB_BITS] >> (nbits % LIMB_BITS)) & 1 == 1
+    };
+    let result = signextend(&masked(&raw, nbits), nbits);
+
+    let a_sign = sign_bit(a, nbits);
+    let b_sign = sign_bit(b, nbits) ^ sub;
+    let result_sign = sign_bit(&result, nbits);
+    let overflow = (a_sign == b_sign) && (result_sign != a_sign);
+
+    let zero = masked(&result, nbits).iter().all(|&limb| limb == 0);
+    (
+        result,
+        AluFlags {
+            carry,
+            overflow,
+            zero,
+            negative: result_sign,
+        },
+    )
+}
+
+fn alu_mul(a: &[u64], b: &[u64], nbits: usize) -> (Vec<u64>, AluFlags) {
+    let (a_neg, a_mag) = magnitude_and_sign(a, nbits);
+    let (b_neg, b_mag) = magnitude_and_sign(b, nbits);
+    let product_mag = mul_limbs(&a_mag, &b_mag);
+    let result_neg = a_neg ^ b_neg;
+
+    let raw = if result_neg {
+        twoscomp(&product_mag)
+    } else {
+        product_mag.clone()
+    };
+    let carry = bits_above(&mul_limbs(&masked(a, nbits), &masked(b, nbits)), nbits);
+    let result = signextend(&masked(&raw, nbits), nbits);
+
+    // The true signed product fits in `nbits` iff its magnitude fits
+    // within the representable range for its sign: up to 2^(nbits-1) - 1
+    // for a positive result, or 2^(nbits-1) for a negative one (the most
+    // negative number has no positive complement).
+    let limit = shl(&[1u64], nbits - 1);
+    let fits = if result_neg {
+        cmp_limbs(&product_mag, &limit) != std::cmp::Ordering::Greater
+    } else {
+        cmp_limbs(&product_mag, &limit) == std::cmp::Ordering::Less
+    };
+
+    let result_sign = sign_bit(&result, nbits);
+    let zero = result.iter().all(|&limb| limb == 0);
+    (
+        result,
+        AluFlags {
+            carry,
+            overflow: !fits,
+            zero,
+            negative: result_sign,
+        },
+    )
+}
+
+/// The wrapped, unsigned residue of `a - b` modulo `2^nbits`.
+fn wrapped_diff(a: &[u64], b: &[u64], nbits: usize) -> Vec<u64> {
+    let a_m = masked(a, nbits);
+    let neg_b = masked(&twoscomp(&masked(b, nbits)), nbits);
+    let wide = nlimbs(nbits) + 1;
+    let mut a_wide = vec![0u64; wide];
+    a_wide[..a_m.len()].copy_from_slice(&a_m);
+    let mut b_wide = vec![0u64; wide];
+    b_wide[..neg_b.len()].copy_from_slice(&neg_b);
+    masked(&wrapping_add(&a_wide, &b_wide), nbits)
+}
+
+/// The smallest signed representative of `a - b` modulo `2^nbits`: the
+/// forward residue `d0 = (a - b) mod 2^n`, the backward residue
+/// `d1 = (b - a) mod 2^n`, and whichever of `d0` (as-is) or `d1` (negated)
+/// has the smaller magnitude, sign-extended to `nbits`. This accounts for
+/// wrap-around: at 16 bits, `d0` for `(0x0000 - 0xffff)` is `1`, not the
+/// naive, unwrapped `-65535`.
+pub fn dist(a: &[u64], b: &[u64], nbits: usize) -> (Vec<u64>, Vec<u64>, Vec<u64>) {
+    let d0 = wrapped_diff(a, b, nbits);
+    let d1 = wrapped_diff(b, a, nbits);
+    let result = if cmp_limbs(&d0, &d1) != std::cmp::Ordering::Greater {
+        signextend(&d0, nbits)
+    } else {
+        signextend(&twoscomp(&d1), nbits)
+    };
+    (d0, d1, result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_clears_high_bits() {
+        assert_eq!(0xffu8.mask(4), 0x0f);
+        assert_eq!(0xffffu16.mask(12), 0x0fff);
+    }
+
+    #[test]
+    fn sign_extend_fills_high_bits_when_negative() {
+        assert_eq!(0x8u8.sign_extend(4), 0xf8);
+        assert_eq!(0x7u8.sign_extend(4), 0x07);
+    }
+
+    #[test]
+    fn twos_complement_round_trips() {
+        assert_eq!(10u32.twos_complement(12).twos_complement(12), 10u32);
+        assert_eq!((-10i32).twos_complement(8) as u8, 10u8);
+    }
+
+    #[test]
+    fn most_negative_number_has_no_positive_complement() {
+        // i8::MIN (-128) is its own two's complement: it has no positive
+        // representation at 8 bits.
+        assert_eq!(i8::MIN.twos_complement(8), i8::MIN);
+        assert_eq!(i32::MIN.twos_complement(32), i32::MIN);
+    }
+
+    #[test]
+    fn parse_num_round_trips_basic_values() {
+        let num = parse_num("100", 8, 0).unwrap();
+        assert_eq!(decimal(&num, 8), "100");
+        let neg = parse_num("-1", 8, 0).unwrap();
+        assert_eq!(hex(&neg, 8), "ff");
+    }
+
+    #[test]
+    fn parse_num_rejects_out_of_range_values() {
+        assert!(matches!(
+            parse_num("300", 8, 0),
+            Err(TwosCompError::OutOfRange(8))
+        ));
+    }
+
+    #[test]
+    fn fixed_decimal_formats_fractional_values() {
+        let num = parse_num("-3.25", 32, 8).unwrap();
+        assert_eq!(fixed_decimal(&num, 32, 8), "-3.250");
+    }
+
+    #[test]
+    fn alu_add_sets_carry_on_unsigned_overflow() {
+        let a = parse_num("200", 8, 0).unwrap();
+        let b = parse_num("100", 8, 0).unwrap();
+        let (result, flags) = alu(AluOp::Add, &a, &b, 8);
+        assert_eq!(decimal(&result, 8), "44");
+        assert!(flags.carry);
+        assert!(!flags.overflow);
+    }
+
+    #[test]
+    fn alu_add_sets_signed_overflow() {
+        let a = parse_num("100", 8, 0).unwrap();
+        let b = parse_num("50", 8, 0).unwrap();
+        let (result, flags) = alu(AluOp::Add, &a, &b, 8);
+        assert_eq!(decimal(&result, 8), "-106");
+        assert!(!flags.carry);
+        assert!(flags.overflow);
+    }
+
+    #[test]
+    fn alu_sub_detects_zero() {
+        let a = parse_num("42", 8, 0).unwrap();
+        let b = parse_num("42", 8, 0).unwrap();
+        let (result, flags) = alu(AluOp::Sub, &a, &b, 8);
+        assert!(flags.zero);
+        assert!(!flags.negative);
+        assert_eq!(decimal(&result, 8), "0");
+    }
+
+    #[test]
+    fn alu_mul_detects_signed_overflow() {
+        let a = parse_num("100", 8, 0).unwrap();
+        let b = parse_num("2", 8, 0).unwrap();
+        let (_, flags) = alu(AluOp::Mul, &a, &b, 8);
+        assert!(flags.overflow);
+    }
+
+    #[test]
+    fn dist_accounts_for_wrap_around() {
+        // 0x0000 - 0xffff wraps to 1, not the naive unwrapped -65535.
+        let a = parse_num("0", 16, 0).unwrap();
+        let b = parse_num("0xffff", 16, 0).unwrap();
+        let (_, _, result) = dist(&a, &b, 16);
+        assert_eq!(decimal(&result, 16), "1");
+    }
+
+    #[test]
+    fn dist_is_antisymmetric() {
+        let a = parse_num("10", 16, 0).unwrap();
+        let b = parse_num("20", 16, 0).unwrap();
+        let (_, _, result) = dist(&a, &b, 16);
+        assert_eq!(decimal(&result, 16), "-10");
+        let (_, _, result) = dist(&b, &a, 16);
+        assert_eq!(decimal(&result, 16), "10");
+    }
+
+    #[test]
+    fn octal_renders_zero_padded_digits() {
+        let num = parse_num("8", 8, 0).unwrap();
+        assert_eq!(octal(&num, 8), "010");
+    }
+
+    #[test]
+    fn unsigned_decimal_matches_naive_conversion_across_chunk_boundary() {
+        let num = parse_num("123456789", 64, 0).unwrap();
+        assert_eq!(decimal(&num, 64), "123456789");
+    }
+
+    #[test]
+    fn group_inserts_separators_from_the_right() {
+        assert_eq!(group("1234567", 3, ','), "1,234,567");
+        assert_eq!(group("-1234567", 3, ','), "-1,234,567");
+        assert_eq!(group("1010", 4, ' '), "1010");
+        assert_eq!(group("10101010", 4, ' '), "1010 1010");
+    }
+}
@@ -1,92 +1,170 @@
-//! Demonstrate 2's compliment integer
-//!
-//! "2's Compliment" is a representation for integers such that,
-//! for any integer k of width n, k's negative "-k" is its
-//! compliment modulo 2^n.  That is, for any `k`, `-k` is the
-//! number so that $k + (-k) == 2^n$.  Another way to look at
-//! this is that $(k + (-k)) mod 2^n == 0$.
-//!
-//! Several desirable properties fall out of this definition:
-//!
-//! 1. Zero has a single representation.  Since the compliment
-//!    of 0 is 2^n, -0 is simply 0.
-//! 2. The same arithmetic logic for arithmetic may be used for
-//!    both signed and unsigned arithmetic.
-//!
-//! Note that there is one odd case; the most negative number
-//! has no positive compliment.
+use twoscomp::{
+    alu, binary, dist, fixed_decimal, group, hex, mask, octal, parse_num, parse_width, signextend,
+    twoscomp, unsigned_decimal, AluOp,
+};
 
-use std::num::ParseIntError;
+/// Digit grouping sizes used when `--group` is given: binary groups by
+/// nibble, hex by four digits, octal by three digits, decimal by thousands.
+const BIN_GROUP: usize = 4;
+const OCT_GROUP: usize = 3;
+const HEX_GROUP: usize = 4;
+const DEC_GROUP: usize = 3;
 
-fn parse_num(num: &str) -> Result<u128, ParseIntError> {
-    let pos = !num.starts_with('-');
-    let (radix, numstr) = match &num[if pos { 0 } else { 1 }..] {
-        "0" => (10, "0"),
-        s if s.starts_with("0x") || s.starts_with("0X") => (16, &s[2..]),
-        s if s.starts_with("0t") || s.starts_with("0T") => (10, &s[2..]),
-        s if s.starts_with("0b") || s.starts_with("0B") => (2, &s[2..]),
-        s if s.starts_with("0") => (8, &s[0..]),
-        s => (10, s),
-    };
-    let num = u128::from_str_radix(numstr, radix)?;
-    Ok(if pos { num } else { 0u128.wrapping_sub(num) })
+fn parse_nbits(widthstr: &str) -> (usize, usize) {
+    let (nbits, fbits) = parse_width(widthstr).unwrap_or_else(|e| {
+        eprintln!("twoscomp: failed to parse width {widthstr}: {e}");
+        std::process::exit(1);
+    });
+    if !nbits.is_power_of_two() {
+        eprintln!("twoscomp: number of bits not a power of two: {nbits}");
+        std::process::exit(1);
+    }
+    if !(4..=(1 << 20)).contains(&nbits) {
+        eprintln!("twoscomp: number of bits out of range (4-1048576): {nbits}");
+        std::process::exit(1);
+    }
+    if fbits >= nbits {
+        eprintln!("twoscomp: fractional bits {fbits} not less than width {nbits}");
+        std::process::exit(1);
+    }
+    (nbits, fbits)
 }
 
-fn signextend(n: u128, nbits: usize) -> u128 {
-    let mask = !0u128 >> (128 - nbits);
-    let neg = (n >> (nbits - 1)) & 0b1 == 1;
-    if neg {
-        n | !mask
+fn parse_operand(numstr: &str, nbits: usize, fbits: usize) -> Vec<u64> {
+    parse_num(numstr, nbits, fbits).unwrap_or_else(|e| {
+        eprintln!("twoscomp: number {numstr}: {e}");
+        std::process::exit(1);
+    })
+}
+
+fn show(label: &str, limbs: &[u64], nbits: usize, fbits: usize, suffix: &str, grouped: bool) {
+    let mut disp = limbs.to_vec();
+    mask(&mut disp, nbits);
+    let value = fixed_decimal(limbs, nbits, fbits);
+    if grouped {
+        show_radixes(label, &disp, nbits, &value, suffix);
     } else {
-        n & mask
+        println!(
+            "{label:<8} 0x{} ({})  [{value}{suffix}]",
+            hex(&disp, nbits),
+            binary(&disp, nbits)
+        );
     }
 }
 
-fn twoscomp(n: u128) -> u128 {
-    let onescomp = !n;
-    onescomp.wrapping_add(1)
+fn show_unsigned(label: &str, limbs: &[u64], nbits: usize, grouped: bool) {
+    let mut disp = limbs.to_vec();
+    mask(&mut disp, nbits);
+    let value = unsigned_decimal(&disp);
+    if grouped {
+        show_radixes(label, &disp, nbits, &value, "");
+    } else {
+        println!(
+            "{label:<8} 0x{} ({})  [{value}]",
+            hex(&disp, nbits),
+            binary(&disp, nbits)
+        );
+    }
 }
 
-fn main() {
-    let args: Vec<_> = std::env::args().collect();
-    if args.len() != 3 {
-        eprintln!("Usage: twoscomp bits num");
-        std::process::exit(1);
-    }
-    let nbitstr = &args[1];
-    let nbits = nbitstr.parse::<usize>().unwrap_or_else(|e| {
-        eprintln!("twoscomp: failed to parse nbits {nbitstr}: {e:?}");
-        std::process::exit(1);
-    });
-    if !nbits.is_power_of_two() {
-        eprintln!("twoscomp: number of bits not a power of two: {nbits}");
-        std::process::exit(1);
-    }
-    if !(4..=128).contains(&nbits) {
-        eprintln!("twoscomp: number of bits out of range (4-128): {nbits}");
+/// Print `limbs` in binary, octal, decimal and hex, each grouped into
+/// readable chunks; `decimal` is the plain (ungrouped) decimal string,
+/// possibly with a fractional `.digits` part left ungrouped, with `suffix`
+/// appended verbatim afterward.
+fn show_radixes(label: &str, limbs: &[u64], nbits: usize, decimal: &str, suffix: &str) {
+    let (int_part, frac_part) = match decimal.split_once('.') {
+        Some((int_part, frac_part)) => (int_part, format!(".{frac_part}")),
+        None => (decimal, String::new()),
+    };
+    println!("{label}");
+    println!("  bin: {}", group(&binary(limbs, nbits), BIN_GROUP, ' '));
+    println!("  oct: {}", group(&octal(limbs, nbits), OCT_GROUP, ' '));
+    println!(
+        "  dec: {}{frac_part}{suffix}",
+        group(int_part, DEC_GROUP, ',')
+    );
+    println!("  hex: {}", group(&hex(limbs, nbits), HEX_GROUP, ' '));
+}
+
+fn twoscomp_mode(widthstr: &str, numstr: &str, grouped: bool) {
+    let (nbits, fbits) = parse_nbits(widthstr);
+    let num = parse_operand(numstr, nbits, fbits);
+    let n2c = signextend(&twoscomp(&num), nbits);
+    show(
+        "number:",
+        &num,
+        nbits,
+        fbits,
+        &format!(" from {numstr}"),
+        grouped,
+    );
+    show("2s cmpl:", &n2c, nbits, fbits, "", grouped);
+}
+
+fn alu_mode(widthstr: &str, opstr: &str, astr: &str, bstr: &str, grouped: bool) {
+    let (nbits, fbits) = parse_nbits(widthstr);
+    if fbits != 0 {
+        eprintln!("twoscomp: ALU mode does not support fractional widths");
         std::process::exit(1);
     }
-    let width = nbits / 4;
-    let numstr = &args[2];
-    let num = parse_num(numstr).unwrap_or_else(|e| {
-        eprintln!("twoscomp: failed to parse number {numstr}: {e:?}");
-        std::process::exit(1);
-    });
-    let senum = signextend(num, nbits);
-    if num != senum && num >> nbits != 0 {
-        eprintln!("twoscomp: number {numstr} out of range for width {nbits} bits");
+    let op = match opstr {
+        "add" => AluOp::Add,
+        "sub" => AluOp::Sub,
+        "mul" => AluOp::Mul,
+        _ => unreachable!("dispatched only for add, sub or mul"),
+    };
+    let a = parse_operand(astr, nbits, 0);
+    let b = parse_operand(bstr, nbits, 0);
+    let (result, flags) = alu(op, &a, &b, nbits);
+
+    show("a:", &a, nbits, 0, "", grouped);
+    show("b:", &b, nbits, 0, "", grouped);
+    show("result:", &result, nbits, 0, "", grouped);
+    println!(
+        "flags:   carry={} overflow={} zero={} negative={}",
+        flags.carry as u8, flags.overflow as u8, flags.zero as u8, flags.negative as u8
+    );
+}
+
+fn dist_mode(widthstr: &str, astr: &str, bstr: &str, grouped: bool) {
+    let (nbits, fbits) = parse_nbits(widthstr);
+    if fbits != 0 {
+        eprintln!("twoscomp: dist mode does not support fractional widths");
         std::process::exit(1);
     }
-    let num = senum;
-    let n2c = signextend(twoscomp(num), nbits);
+    let a = parse_operand(astr, nbits, 0);
+    let b = parse_operand(bstr, nbits, 0);
+    let (d0, d1, result) = dist(&a, &b, nbits);
 
-    // Signed, for printing as decimal.
-    let snum = num as i128;
-    let sn2c = n2c as i128;
+    show_unsigned("d0:", &d0, nbits, grouped);
+    show_unsigned("d1:", &d1, nbits, grouped);
+    show("dist:", &result, nbits, 0, "", grouped);
+}
 
-    let mask = !0u128 >> (128 - nbits);
-    let num = num & mask;
-    let n2c = n2c & mask;
-    println!("number:  0x{num:0>width$x} ({num:0>nbits$b})  [{snum} from {numstr}]");
-    println!("2s cmpl: 0x{n2c:0>width$x} ({n2c:0>nbits$b})  [{sn2c}]");
+fn main() {
+    let mut args: Vec<_> = std::env::args().collect();
+    let grouped = match args.iter().position(|a| a == "--group") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+    match args.len() {
+        3 => twoscomp_mode(&args[1], &args[2], grouped),
+        5 => match args[2].as_str() {
+            "add" | "sub" | "mul" => alu_mode(&args[1], &args[2], &args[3], &args[4], grouped),
+            "dist" => dist_mode(&args[1], &args[3], &args[4], grouped),
+            op => {
+                eprintln!("twoscomp: unknown operation {op} (expected add, sub, mul or dist)");
+                std::process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("Usage: twoscomp [--group] bits[.fbits] num");
+            eprintln!("       twoscomp [--group] bits add|sub|mul a b");
+            eprintln!("       twoscomp [--group] bits dist a b");
+            std::process::exit(1);
+        }
+    }
 }